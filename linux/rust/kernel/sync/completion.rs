@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A completion.
+//!
+//! This module mirrors the kernel's [`struct completion`]: a wait queue paired with a `done`
+//! counter. Because the count persists, a completion signalled before any thread waits is not
+//! lost, unlike a bare [`CondVar`] notification.
+//!
+//! [`struct completion`]: ../../../include/linux/completion.h
+//! [`CondVar`]: super::CondVar
+
+use super::condvar::CondVarWaitResult;
+use super::{CondVar, Mutex};
+use crate::{bindings, init::PinInit, new_condvar, new_mutex, time::Jiffies};
+
+/// The value `done` is raised to by [`Completion::complete_all`], meaning "completed for good".
+///
+/// It is never decremented by a waiter, so every present and future waiter proceeds immediately.
+const COMPLETE_ALL: u32 = u32::MAX;
+
+/// A synchronisation primitive that lets one or more threads wait for an event to be signalled.
+///
+/// Signalling is sticky: [`Completion::complete`] raises an internal counter and wakes a single
+/// waiter, so a signal that arrives before any waiter sleeps is remembered rather than dropped.
+/// [`Completion::complete_all`] marks the completion done permanently.
+#[pin_data]
+pub struct Completion {
+    /// The number of outstanding completions; `COMPLETE_ALL` means "done for good".
+    #[pin]
+    done: Mutex<u32>,
+
+    /// Waiters sleep here while `done` is zero.
+    #[pin]
+    cv: CondVar,
+}
+
+impl Completion {
+    /// Constructs a pinned initialiser for a new, not-yet-signalled completion.
+    pub fn new() -> impl PinInit<Self> {
+        pin_init!(Self {
+            done <- new_mutex!(0, "Completion::done"),
+            cv <- new_condvar!("Completion::cv"),
+        })
+    }
+
+    /// Signals the completion, waking one waiter. The signal is remembered until a waiter consumes
+    /// it, so it is never lost even if no thread is currently waiting.
+    pub fn complete(&self) {
+        let mut done = self.done.lock();
+        if *done != COMPLETE_ALL {
+            *done = done.saturating_add(1);
+        }
+        self.cv.notify_one();
+    }
+
+    /// Signals the completion for good, waking every current and future waiter.
+    pub fn complete_all(&self) {
+        let mut done = self.done.lock();
+        *done = COMPLETE_ALL;
+        self.cv.notify_all();
+    }
+
+    /// Waits until the completion is signalled, consuming one signal. Sleeps uninterruptibly, so
+    /// it is not woken by signals.
+    pub fn wait_for_completion(&self) {
+        let mut done = self.done.lock();
+        while *done == 0 {
+            self.cv.wait_uninterruptible(&mut done);
+        }
+        if *done != COMPLETE_ALL {
+            *done -= 1;
+        }
+    }
+
+    /// Like [`Completion::wait_for_completion`], but sleeps interruptibly and bails out if a
+    /// signal becomes pending. Returns `true` if it was interrupted by a signal (in which case no
+    /// completion was consumed), or `false` once the completion was signalled and consumed.
+    ///
+    /// Built on [`CondVar::wait_until`], so the predicate is rechecked under the lock on every
+    /// wakeup, absorbing spurious wakeups.
+    ///
+    /// [`CondVar::wait_until`]: super::CondVar::wait_until
+    #[must_use = "the caller must handle an interrupting signal"]
+    pub fn wait_for_completion_interruptible(&self) -> bool {
+        let mut done = self.done.lock();
+        if self.cv.wait_until(&mut done, |done| **done != 0) {
+            return true;
+        }
+        if *done != COMPLETE_ALL {
+            *done -= 1;
+        }
+        false
+    }
+
+    /// Like [`Completion::wait_for_completion`], but gives up after at most `timeout` jiffies.
+    ///
+    /// Returns the number of jiffies left on the clock (`Jiffies(0)` if the timeout elapsed before
+    /// the completion was signalled).
+    pub fn wait_for_completion_timeout(&self, timeout: Jiffies) -> Jiffies {
+        let mut done = self.done.lock();
+        let mut remaining = timeout;
+        while *done == 0 {
+            match self
+                .cv
+                .wait_internal(bindings::TASK_UNINTERRUPTIBLE, &mut done, remaining)
+            {
+                CondVarWaitResult::Woken { remaining: left } => remaining = left,
+                // Uninterruptible sleeps never report a signal; only the timeout can fire here.
+                CondVarWaitResult::TimedOut | CondVarWaitResult::Signal => return Jiffies(0),
+            }
+        }
+        if *done != COMPLETE_ALL {
+            *done -= 1;
+        }
+        remaining
+    }
+}
@@ -5,15 +5,44 @@
 //! This module allows Rust code to use the kernel's [`struct wait_queue_head`] as a condition
 //! variable.
 
-use super::{Guard, Lock, LockClassKey, LockInfo, NeedsLockClass};
-use crate::{bindings, pr_info, str::CStr, task::Task, Opaque};
-use core::{marker::PhantomPinned, pin::Pin};
+use super::{Guard, Lock, LockClassKey, LockInfo};
+use crate::{bindings, init::PinInit, pr_info, str::CStr, task::Task, time::Jiffies, Opaque};
+use core::marker::PhantomPinned;
 
-/// Safely initialises a [`CondVar`] with the given name, generating a new lock class.
+/// Sentinel timeout meaning "sleep indefinitely", mirroring the kernel's `MAX_SCHEDULE_TIMEOUT`.
+///
+/// When passed to [`CondVar::wait_timeout`] it is forwarded to `schedule_timeout` unchanged, so
+/// the thread sleeps until it is notified or receives a signal, exactly like [`CondVar::wait`].
+pub const MAX_SCHEDULE_TIMEOUT: Jiffies = Jiffies(core::ffi::c_long::MAX);
+
+/// The outcome of a timed wait on a [`CondVar`].
+pub enum CondVarWaitResult {
+    /// The thread was woken by a notification with the given number of jiffies left on the clock.
+    Woken {
+        /// Jiffies remaining until the timeout would have expired.
+        remaining: Jiffies,
+    },
+    /// The timeout elapsed before the thread was notified.
+    TimedOut,
+    /// A signal is pending for the current task; it takes priority over the other outcomes.
+    Signal,
+}
+
+/// Constructs a pinned initialiser for a new [`CondVar`], generating a fresh lock class.
+///
+/// It expands to a call to [`CondVar::new`], supplying an optional name (defaulting to the caller
+/// location) and a new static lock class. The result is a [`PinInit`] suitable for
+/// `stack_pin_init!` or [`Box::pin_init`], so the condvar can be embedded directly in a
+/// `#[pin_data]` struct with no separate initialisation step.
+///
+/// [`Box::pin_init`]: crate::init::InPlaceInit::pin_init
 #[macro_export]
-macro_rules! condvar_init {
-    ($condvar:expr, $name:literal) => {
-        $crate::init_with_lockdep!($condvar, $name)
+macro_rules! new_condvar {
+    ($($name:literal)?) => {
+        $crate::sync::CondVar::new(
+            $crate::optional_name!($($name)?),
+            $crate::static_lock_class!(),
+        )
     };
 }
 
@@ -43,15 +72,24 @@ unsafe impl Send for CondVar {}
 unsafe impl Sync for CondVar {}
 
 impl CondVar {
-    /// Constructs a new conditional variable.
-    ///
-    /// # Safety
+    /// Constructs a pinned initialiser for a new conditional variable.
     ///
-    /// The caller must call `CondVar::init` before using the conditional variable.
-    pub const unsafe fn new() -> Self {
-        Self {
-            wait_list: Opaque::uninit(),
-            _pin: PhantomPinned,
+    /// The returned initialiser wires up the underlying `struct wait_queue_head` in place via
+    /// `__init_waitqueue_head`, so construction is safe and one-step. Prefer the [`new_condvar!`]
+    /// macro, which fills in the name and lock class for you.
+    pub fn new(name: &'static CStr, key: &'static LockClassKey) -> impl PinInit<Self> {
+        // SAFETY: The closure initialises `wait_list` in place. The initialiser is pinned, so the
+        // self-referential list inside the wait queue is never moved after initialisation.
+        unsafe {
+            crate::init::pin_init_from_closure(move |slot: *mut Self| {
+                let wait_list = core::ptr::addr_of_mut!((*slot).wait_list);
+                bindings::__init_waitqueue_head(
+                    (*wait_list).get(),
+                    name.as_char_ptr(),
+                    key.get(),
+                );
+                Ok(())
+            })
         }
     }
 
@@ -62,6 +100,93 @@ impl CondVar {
     /// Returns whether there is a signal pending.
     #[must_use = "wait returns if a signal is pending, so the caller must check the return value"]
     pub fn wait<L: Lock<I>, I: LockInfo>(&self, guard: &mut Guard<'_, L, I>) -> bool {
+        matches!(
+            self.wait_timeout(guard, MAX_SCHEDULE_TIMEOUT),
+            CondVarWaitResult::Signal
+        )
+    }
+
+    /// Like [`CondVar::wait`], but gives up after at most `timeout` jiffies.
+    ///
+    /// Pass [`MAX_SCHEDULE_TIMEOUT`] to sleep indefinitely, which reproduces [`CondVar::wait`].
+    /// The returned [`CondVarWaitResult`] distinguishes a genuine notification (with the number of
+    /// jiffies left on the clock), the timeout elapsing, and a pending signal; the signal takes
+    /// priority over the other two outcomes.
+    pub fn wait_timeout<L: Lock<I>, I: LockInfo>(
+        &self,
+        guard: &mut Guard<'_, L, I>,
+        timeout: Jiffies,
+    ) -> CondVarWaitResult {
+        self.wait_internal(bindings::TASK_INTERRUPTIBLE, guard, timeout)
+    }
+
+    /// Waits like [`CondVar::wait`] but in the `TASK_UNINTERRUPTIBLE` state, so no signal, fatal
+    /// or not, wakes the thread. Useful for sleeps that must not be torn apart by arbitrary signals,
+    /// such as waiting for a mid-transaction completion. Never reports a pending signal.
+    pub fn wait_uninterruptible<L: Lock<I>, I: LockInfo>(&self, guard: &mut Guard<'_, L, I>) {
+        let _ = self.wait_internal(bindings::TASK_UNINTERRUPTIBLE, guard, MAX_SCHEDULE_TIMEOUT);
+    }
+
+    /// Waits like [`CondVar::wait`] but in the `TASK_KILLABLE` state, so the kernel only wakes the
+    /// thread on a fatal (process kill) signal while ignoring ordinary ones. Returns whether a
+    /// signal is pending; note the [`Task`] abstraction exposes only `signal_pending`, so this
+    /// reports any pending signal, not strictly a fatal one.
+    #[must_use = "wait_killable returns if a signal is pending, so the caller must check it"]
+    pub fn wait_killable<L: Lock<I>, I: LockInfo>(&self, guard: &mut Guard<'_, L, I>) -> bool {
+        matches!(
+            self.wait_internal(bindings::TASK_KILLABLE, guard, MAX_SCHEDULE_TIMEOUT),
+            CondVarWaitResult::Signal
+        )
+    }
+
+    /// Sleeps as long as `condition` holds, re-checking it under the reacquired lock after every
+    /// wakeup. This is the safe monitor pattern: spurious and lost wakeups are absorbed because
+    /// the predicate is evaluated against the shared state on each pass rather than trusting a
+    /// single notification.
+    ///
+    /// Returns `true` if it stopped because a signal became pending (the predicate may still hold
+    /// in that case), and `false` once `condition` evaluated to `false`.
+    #[must_use = "wait_while returns if a signal is pending, so the caller must check it"]
+    pub fn wait_while<L: Lock<I>, I: LockInfo, F: FnMut(&Guard<'_, L, I>) -> bool>(
+        &self,
+        guard: &mut Guard<'_, L, I>,
+        mut condition: F,
+    ) -> bool {
+        while condition(guard) {
+            if self.wait(guard) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Sleeps until `condition` holds, the inverse convenience of [`CondVar::wait_while`].
+    ///
+    /// Returns `true` if it stopped because a signal became pending, `false` once `condition`
+    /// evaluated to `true`.
+    #[must_use = "wait_until returns if a signal is pending, so the caller must check it"]
+    pub fn wait_until<L: Lock<I>, I: LockInfo, F: FnMut(&Guard<'_, L, I>) -> bool>(
+        &self,
+        guard: &mut Guard<'_, L, I>,
+        mut condition: F,
+    ) -> bool {
+        self.wait_while(guard, |guard| !condition(guard))
+    }
+
+    /// Shared sleep path for the `wait*` family. Releases the lock, sleeps in the requested task
+    /// `state` for up to `timeout` jiffies, then reacquires the lock. A pending signal is only
+    /// reported when `state` allows it to wake the thread (interruptible or killable sleeps).
+    ///
+    /// Crate-visible so other sync primitives (e.g. [`Completion`]) can pick an arbitrary task
+    /// state and timeout in their own wait loops.
+    ///
+    /// [`Completion`]: super::Completion
+    pub(crate) fn wait_internal<L: Lock<I>, I: LockInfo>(
+        &self,
+        state: u32,
+        guard: &mut Guard<'_, L, I>,
+        timeout: Jiffies,
+    ) -> CondVarWaitResult {
         let lock = guard.lock;
         pr_info!("define lock\n");
         let wait = Opaque::<bindings::wait_queue_entry>::uninit();
@@ -74,11 +199,7 @@ impl CondVar {
         // SAFETY: Both `wait` and `wait_list` point to valid memory.
         pr_info!("{:?}, {:?}", self.wait_list.get(), wait.get());
         unsafe {
-            bindings::prepare_to_wait_exclusive(
-                self.wait_list.get(),
-                wait.get(),
-                bindings::TASK_INTERRUPTIBLE as _,
-            )
+            bindings::prepare_to_wait_exclusive(self.wait_list.get(), wait.get(), state as _)
         };
         pr_info!("prepare_to_wait_exclusive\n");
 
@@ -86,9 +207,10 @@ impl CondVar {
         unsafe { lock.unlock(&mut guard.context) };
 
         pr_info!("unlock\n");
-        // SAFETY: No arguments, switches to another thread.
-        unsafe { bindings::schedule() };
-        pr_info!("schedule\n");
+        // SAFETY: No arguments besides the timeout, switches to another thread. Returns the number
+        // of jiffies left until the timeout would have expired (0 if it fully elapsed).
+        let remaining = unsafe { bindings::schedule_timeout(timeout.as_long()) };
+        pr_info!("schedule_timeout\n");
 
         guard.context = lock.lock_noguard();
         pr_info!("lock_noguard\n");
@@ -97,7 +219,21 @@ impl CondVar {
         unsafe { bindings::finish_wait(self.wait_list.get(), wait.get()) };
         pr_info!("finish_wait\n");
 
-        Task::current().signal_pending()
+        // A signal is only reported for a sleep the kernel would let a signal wake: interruptible
+        // (any signal) or killable (fatal signal). An uninterruptible sleep never reports one.
+        // The `Task` abstraction only exposes `signal_pending`, so the killable case reports any
+        // pending signal rather than strictly a fatal one.
+        let signal_may_wake = state & (bindings::TASK_INTERRUPTIBLE | bindings::TASK_WAKEKILL) != 0;
+
+        if signal_may_wake && Task::current().signal_pending() {
+            CondVarWaitResult::Signal
+        } else if remaining == 0 {
+            CondVarWaitResult::TimedOut
+        } else {
+            CondVarWaitResult::Woken {
+                remaining: Jiffies(remaining),
+            }
+        }
     }
 
     /// Calls the kernel function to notify the appropriate number of threads with the given flags.
@@ -134,16 +270,3 @@ impl CondVar {
         self.notify(1, bindings::POLLHUP | POLLFREE);
     }
 }
-
-impl NeedsLockClass for CondVar {
-    fn init(
-        self: Pin<&mut Self>,
-        name: &'static CStr,
-        key: &'static LockClassKey,
-        _: &'static LockClassKey,
-    ) {
-        unsafe {
-            bindings::__init_waitqueue_head(self.wait_list.get(), name.as_char_ptr(), key.get())
-        };
-    }
-}
@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel time and timers.
+//!
+//! This module wraps the kernel's time-related types so they can be used from Rust code.
+
+use core::ffi::c_long;
+
+/// A number of jiffies, the kernel's coarse timer tick (one jiffy is `1/HZ` seconds).
+///
+/// Used to express relative timeouts for sleeping primitives such as [`CondVar::wait_timeout`].
+///
+/// [`CondVar::wait_timeout`]: crate::sync::CondVar::wait_timeout
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Jiffies(pub c_long);
+
+impl Jiffies {
+    /// Returns the raw jiffy count as the `c_long` expected by the C timer APIs.
+    pub(crate) fn as_long(self) -> c_long {
+        self.0
+    }
+}
@@ -4,7 +4,8 @@
 use core::result::Result::Err;
 
 use kernel::prelude::*;
-use kernel::sync::{Mutex, CondVar};
+use kernel::error::code::EINTR;
+use kernel::sync::{Completion, Mutex};
 use kernel::{chrdev, file};
 
 const GLOBALMEM_SIZE: usize = 0x64;
@@ -18,9 +19,10 @@ module! {
 static GLOBALMEM_BUF: Mutex<[u8;GLOBALMEM_SIZE]> = unsafe {
     Mutex::new([0u8;GLOBALMEM_SIZE])
 };
-static GLOBAL_CV: CondVar = unsafe {
-    CondVar::new()
-};
+// Pin-initialised once at module load (see `init`) and kept alive until unload, so every open
+// file shares the same completion. The count persists, so a writer that completes before any
+// reader sleeps is not lost.
+static mut GLOBAL_COMPLETION: Option<Pin<Box<Completion>>> = None;
 
 struct RustCompletion {
     _dev: Pin<Box<chrdev::Registration<1>>>,
@@ -32,6 +34,12 @@ impl kernel::Module for RustCompletion {
     fn init(name: &'static CStr, module: &'static ThisModule) -> Result<Self> {
         pr_info!("Rust completion (init): {name}\n");
 
+        // SAFETY: Module init runs once before any file can be opened, so there is no concurrent
+        // access to `GLOBAL_COMPLETION` here.
+        unsafe {
+            GLOBAL_COMPLETION = Some(Box::pin_init(Completion::new())?);
+        }
+
         let mut chrdev_reg = chrdev::Registration::new_pinned(name, 0, module)?;
 
         chrdev_reg.as_mut().register::<RustFile>()?;
@@ -58,6 +66,10 @@ impl kernel::Module for RustCompletion {
 impl Drop for RustCompletion {
     fn drop(&mut self) {
         pr_info!("Rust completion (exit)\n");
+        // SAFETY: Unload runs after every file has been closed, so there is no concurrent access.
+        unsafe {
+            GLOBAL_COMPLETION = None;
+        }
     }
 }
 
@@ -65,8 +77,7 @@ struct RustFile {
     #[allow(dead_code)]
     mutex: &'static Mutex<[u8;GLOBALMEM_SIZE]>,
     // mutex: Pin<Box<Mutex<Vec<u8>>>>,
-    // condvar: Pin<Box<CondVar>>
-    condvar: &'static CondVar
+    completion: &'static Completion
 }
 
 #[vtable]
@@ -79,9 +90,12 @@ impl file::Operations for RustFile {
     // fn open(_shared: &Ref<'a, RustFile>, _file: &file::File) -> Result<Ref<'a, RustFile>> {
         pr_info!("open in chrdev");
         // Ok(_shared.clone())
+        // SAFETY: `GLOBAL_COMPLETION` is set in module init and only cleared on unload, after all
+        // files have been closed, so it is `Some` and lives for `'static` here.
+        let completion: &'static Completion = unsafe { &**GLOBAL_COMPLETION.as_ref().unwrap() };
         Ok(Box::try_new(RustFile {
             mutex: &GLOBALMEM_BUF,
-            condvar: &GLOBAL_CV
+            completion,
         })?)
     }
 
@@ -99,7 +113,10 @@ impl file::Operations for RustFile {
 
         // pr_info("process %d(%s) awakening the readers...\n",
         //     current->pid, current->comm);
-        _this.condvar.notify_all();
+        // Release one waiting reader per write; the count is sticky, so a reader arriving after
+        // the write still consumes this completion instead of blocking. Unlike `complete_all`,
+        // the device returns to blocking once the pending count is drained.
+        _this.completion.complete();
         Ok(_offset as usize)
     }
 
@@ -107,10 +124,13 @@ impl file::Operations for RustFile {
         pr_info!("read in rust_completion\n");
     
         // pr_info("process %d(%s) is going to sleep\n", current->pid, current->comm);
-        let mut guard = _this.mutex.lock();
-        guard[0] = '1' as u8;
-        pr_info!("get guard: {:?}\n", *guard);
-        let _ = _this.condvar.wait(&mut guard);
+        // Sleep until a writer signals the completion, rechecking the count under the lock on
+        // every wakeup. Because the count persists, a completion delivered before this reader
+        // arrived is not lost. Interruptible, so a signal (e.g. Ctrl-C) aborts the read.
+        if _this.completion.wait_for_completion_interruptible() {
+            pr_info!("read interrupted by signal\n");
+            return Err(EINTR);
+        }
         pr_info!("after wait\n");
         // let len = core::cmp::min(_writer.len(), x.len().saturating_sub(_offset as usize));
         // _writer.write_slice(&x[_offset as usize..][..len])?;